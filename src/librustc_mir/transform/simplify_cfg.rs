@@ -38,8 +38,15 @@ use rustc::ty::TyCtxt;
 use rustc::mir::repr::*;
 use rustc::mir::transform::{MirPass, MirSource, Pass};
 use rustc::mir::traversal;
+use std::collections::HashSet;
 use std::fmt;
 
+// bounds for `CfgSimplifier::duplicate_tail`: the largest number of arms
+// we'll privatize a shared tail block into, and the largest number of
+// statements such a block may carry, to keep the duplication bounded.
+const MAX_DUP_PREDS: u32 = 4;
+const MAX_DUP_STMTS: usize = 3;
+
 pub struct SimplifyCfg<'a> { label: &'a str }
 
 impl<'a> SimplifyCfg<'a> {
@@ -66,7 +73,10 @@ impl<'l> Pass for SimplifyCfg<'l> {
 
 pub struct CfgSimplifier<'a, 'tcx: 'a> {
     basic_blocks: &'a mut IndexVec<BasicBlock, BasicBlockData<'tcx>>,
-    pred_count: IndexVec<BasicBlock, u32>
+    pred_count: IndexVec<BasicBlock, u32>,
+    // blocks created by `duplicate_tail`, so we never re-duplicate a
+    // private copy that's already down to a single predecessor.
+    duplicated_tails: HashSet<BasicBlock>,
 }
 
 impl<'a, 'tcx: 'a> CfgSimplifier<'a, 'tcx> {
@@ -87,7 +97,8 @@ impl<'a, 'tcx: 'a> CfgSimplifier<'a, 'tcx> {
 
         CfgSimplifier {
             basic_blocks: basic_blocks,
-            pred_count: pred_count
+            pred_count: pred_count,
+            duplicated_tails: HashSet::new(),
         }
     }
 
@@ -114,7 +125,9 @@ impl<'a, 'tcx: 'a> CfgSimplifier<'a, 'tcx> {
                 while inner_changed {
                     inner_changed = false;
                     inner_changed |= self.simplify_branch(&mut terminator);
+                    inner_changed |= self.simplify_known_branch(&mut terminator);
                     inner_changed |= self.merge_successor(&mut new_stmts, &mut terminator);
+                    inner_changed |= self.duplicate_tail(&mut terminator);
                     changed |= inner_changed;
                 }
 
@@ -124,6 +137,8 @@ impl<'a, 'tcx: 'a> CfgSimplifier<'a, 'tcx> {
                 changed |= inner_changed;
             }
 
+            changed |= self.thread_jumps();
+
             if !changed { break }
         }
     }
@@ -187,6 +202,65 @@ impl<'a, 'tcx: 'a> CfgSimplifier<'a, 'tcx> {
         true
     }
 
+    // merge a block reached only through conditional branch arms (as
+    // opposed to a `Goto`, which `merge_successor` already handles) into
+    // those arms, by giving each arm its own private copy of the block's
+    // statements and terminator. A block with a single such arm is simply
+    // relocated, which frees its old slot for `remove_dead_blocks`; a
+    // block shared between several arms is split into one copy per arm,
+    // bounded by `MAX_DUP_PREDS`, so later passes no longer see it as a
+    // shared, and therefore more conservative, target.
+    fn duplicate_tail(&mut self, terminator: &mut Terminator<'tcx>) -> bool {
+        match terminator.kind {
+            TerminatorKind::If { .. } |
+            TerminatorKind::Switch { .. } |
+            TerminatorKind::SwitchInt { .. } => {}
+            _ => return false,
+        }
+
+        let mut changed = false;
+        for target in terminator.successors_mut() {
+            // a block we already privatized has exactly one predecessor
+            // left and nothing more to gain from being split further.
+            if self.duplicated_tails.contains(target) {
+                continue
+            }
+
+            let count = self.pred_count[*target];
+            if count == 0 || count > MAX_DUP_PREDS {
+                continue
+            }
+            if self.basic_blocks[*target].statements.len() > MAX_DUP_STMTS {
+                continue
+            }
+
+            debug!("duplicating tail block {:?} into its arm", *target);
+            let duplicate = self.basic_blocks[*target].clone();
+            for succ in duplicate.terminator().successors().iter() {
+                self.pred_count[*succ] += 1;
+            }
+            self.pred_count[*target] -= 1;
+            if self.pred_count[*target] == 0 {
+                // the original tail has no live predecessors left, so its
+                // own edges to its successors are dead too; cancel the
+                // contribution they made to `pred_count` when the CFG was
+                // first walked, now that the duplicate's edges (just
+                // counted above) stand in for them.
+                for succ in duplicate.terminator().successors().iter() {
+                    self.pred_count[*succ] -= 1;
+                }
+            }
+
+            let new_target = self.basic_blocks.push(duplicate);
+            self.pred_count.push(1);
+            self.duplicated_tails.insert(new_target);
+            *target = new_target;
+            changed = true;
+        }
+
+        changed
+    }
+
     // turn a branch with all successors identical to a goto
     fn simplify_branch(&mut self, terminator: &mut Terminator<'tcx>) -> bool {
         match terminator.kind {
@@ -214,6 +288,170 @@ impl<'a, 'tcx: 'a> CfgSimplifier<'a, 'tcx> {
         terminator.kind = TerminatorKind::Goto { target: first_succ };
         true
     }
+
+    // fold a branch whose discriminant is a compile-time constant (as can
+    // happen after inlining or constant propagation) into a `Goto` on the
+    // single arm it actually takes.
+    fn simplify_known_branch(&mut self, terminator: &mut Terminator<'tcx>) -> bool {
+        let target = match terminator.kind {
+            TerminatorKind::If { cond: Operand::Constant(ref c), targets: (then_bb, else_bb) } => {
+                match const_bool(c) {
+                    Some(true) => then_bb,
+                    Some(false) => else_bb,
+                    None => return false,
+                }
+            }
+            TerminatorKind::SwitchInt { discr: Operand::Constant(ref c), ref values, ref targets, .. } => {
+                match const_to_u64(c) {
+                    Some(v) => {
+                        let arm = values.iter().position(|switch_val| *switch_val == v);
+                        match arm {
+                            Some(i) => targets[i],
+                            // the last target is the `otherwise` arm.
+                            None => *targets.last().unwrap(),
+                        }
+                    }
+                    None => return false,
+                }
+            }
+            // `TerminatorKind::Switch` (the enum-discriminant dispatch, as
+            // opposed to `SwitchInt`'s integer dispatch) is deliberately
+            // not handled here: `rustc::mir::repr` isn't part of this tree
+            // to confirm against, and unlike `If`/`SwitchInt` its
+            // discriminant has historically been read directly as an
+            // `Lvalue` rather than wrapped in an `Operand`, which would
+            // need a statement-scanning rewrite rather than this simple
+            // terminator-only match.
+            _ => return false,
+        };
+
+        debug!("simplifying known branch {:?}", terminator);
+        // Every successor edge is now dead except the one we keep for
+        // `target`; if `target` showed up more than once among the old
+        // successors (e.g. several switch arms sharing a block), only the
+        // first occurrence survives.
+        let mut kept = false;
+        for succ in terminator.successors().iter().cloned() {
+            if !kept && succ == target {
+                kept = true;
+            } else {
+                self.pred_count[succ] -= 1;
+            }
+        }
+        terminator.kind = TerminatorKind::Goto { target: target };
+        true
+    }
+
+    // thread a `Goto` to a block that immediately branches on a local
+    // through to the arm that local's known constant value takes, e.g.
+    //
+    //     bb1: { _l = const true; goto -> bb2; }
+    //     bb2: { if _l -> [bb3, bb4]; }
+    //
+    // becomes `bb1: { _l = const true; goto -> bb3; }`, and once bb2 has no
+    // other predecessors `remove_dead_blocks` takes care of it.
+    fn thread_jumps(&mut self) -> bool {
+        let mut changed = false;
+
+        for bb in (0..self.basic_blocks.len()).map(BasicBlock::new) {
+            if self.pred_count[bb] == 0 {
+                continue
+            }
+
+            if !self.basic_blocks[bb].statements.is_empty() {
+                continue
+            }
+
+            // `TerminatorKind::Switch` is deliberately not threaded here;
+            // see the note next to its omission in `simplify_known_branch`.
+            let discr = match self.basic_blocks[bb].terminator {
+                Some(Terminator {
+                    kind: TerminatorKind::If { cond: Operand::Consume(ref lvalue), .. }, ..
+                }) |
+                Some(Terminator {
+                    kind: TerminatorKind::SwitchInt { discr: Operand::Consume(ref lvalue), .. }, ..
+                }) => lvalue.clone(),
+                _ => continue,
+            };
+
+            for pred in (0..self.basic_blocks.len()).map(BasicBlock::new) {
+                if pred == bb || self.pred_count[bb] == 0 {
+                    continue
+                }
+
+                let goes_to_bb = match self.basic_blocks[pred].terminator {
+                    Some(Terminator { kind: TerminatorKind::Goto { target }, .. }) => target == bb,
+                    _ => false,
+                };
+                if !goes_to_bb {
+                    continue
+                }
+
+                let constant = match self.basic_blocks[pred].statements.last() {
+                    Some(&Statement {
+                        kind: StatementKind::Assign(ref lvalue, Rvalue::Use(Operand::Constant(ref c))), ..
+                    }) if *lvalue == discr => c.clone(),
+                    _ => continue,
+                };
+
+                let target = match self.basic_blocks[bb].terminator {
+                    Some(ref term) => match branch_target_for_const(&term.kind, &constant) {
+                        Some(target) => target,
+                        None => continue,
+                    },
+                    None => continue,
+                };
+
+                debug!("jump threading {:?} -> {:?} (was -> {:?})", pred, target, bb);
+                if let Some(ref mut term) = self.basic_blocks[pred].terminator {
+                    term.kind = TerminatorKind::Goto { target: target };
+                }
+                self.pred_count[bb] -= 1;
+                self.pred_count[target] += 1;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+// the arm a branch with the given kind takes for a known constant
+// discriminant, if any.
+fn branch_target_for_const(kind: &TerminatorKind, constant: &Constant) -> Option<BasicBlock> {
+    match *kind {
+        TerminatorKind::If { targets: (then_bb, else_bb), .. } => {
+            const_bool(constant).map(|b| if b { then_bb } else { else_bb })
+        }
+        TerminatorKind::SwitchInt { ref values, ref targets, .. } => {
+            const_to_u64(constant).map(|v| {
+                match values.iter().position(|switch_val| *switch_val == v) {
+                    Some(i) => targets[i],
+                    // the last target is the `otherwise` arm.
+                    None => *targets.last().unwrap(),
+                }
+            })
+        }
+        // `TerminatorKind::Switch` (enum-discriminant dispatch) is
+        // deliberately not handled here; see the note next to its
+        // omission in `simplify_known_branch`.
+        _ => None,
+    }
+}
+
+fn const_bool(constant: &Constant) -> Option<bool> {
+    match constant.literal {
+        Literal::Value { value: ConstVal::Bool(b) } => Some(b),
+        _ => None,
+    }
+}
+
+fn const_to_u64(constant: &Constant) -> Option<u64> {
+    match constant.literal {
+        Literal::Value { value: ConstVal::Uint(v) } => Some(v),
+        Literal::Value { value: ConstVal::Int(v) } => Some(v as u64),
+        _ => None,
+    }
 }
 
 fn remove_dead_blocks(mir: &mut Mir) {
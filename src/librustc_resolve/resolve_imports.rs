@@ -32,6 +32,7 @@ use syntax::ast::{NodeId, Name};
 use syntax::attr::AttrMetaMethods;
 use syntax::codemap::Span;
 
+use std::collections::HashMap;
 use std::mem::replace;
 use std::rc::Rc;
 
@@ -86,14 +87,30 @@ pub struct Target {
     pub target_module: Rc<Module>,
     pub ns_def: NsDef,
     pub shadowable: Shadowable,
+    /// Whether this binding was brought in by a glob (`use foo::*;`) rather
+    /// than an explicit `use` or a local item. Glob bindings are the ones
+    /// that silently give way to anything more specific (see the shadowing
+    /// rules in `check_for_conflicting_import`).
+    pub from_glob: bool,
+    /// The span of the import that produced this binding, kept around so
+    /// that ambiguity errors reported later (by `get_binding`) can point
+    /// back at both contributing imports.
+    pub span: Span,
 }
 
 impl Target {
-    pub fn new(target_module: Rc<Module>, ns_def: NsDef, shadowable: Shadowable) -> Target {
+    pub fn new(target_module: Rc<Module>,
+               ns_def: NsDef,
+               shadowable: Shadowable,
+               from_glob: bool,
+               span: Span)
+               -> Target {
         Target {
             target_module: target_module,
             ns_def: ns_def,
             shadowable: shadowable,
+            from_glob: from_glob,
+            span: span,
         }
     }
 }
@@ -116,6 +133,13 @@ pub struct ImportResolution {
     /// Resolution of the name in the namespace
     pub target: Option<Target>,
 
+    /// Set when a *second* glob import brings in a different binding for
+    /// this name than `target` already does. Recording it is not an error by
+    /// itself; `get_binding` reports it when an import in another module
+    /// resolves the name through this one. No other use of the name is
+    /// checked against this field.
+    pub ambiguous: Option<Target>,
+
     /// The source node of the `use` directive
     pub id: NodeId,
 }
@@ -126,6 +150,7 @@ impl ImportResolution {
             outstanding_references: 0,
             id: id,
             target: None,
+            ambiguous: None,
             is_public: is_public,
         }
     }
@@ -146,6 +171,28 @@ struct ImportResolvingError {
 
 struct ImportResolver<'a, 'b: 'a, 'tcx: 'b> {
     resolver: &'a mut Resolver<'b, 'tcx>,
+
+    /// For each module (keyed by `DefId`) that is currently stuck resolving
+    /// one of its own glob imports because the target module still has
+    /// unresolved `pub` imports, this records the target module along with
+    /// the span of the blocking `use ...::*;`. Chasing this map lets us tell
+    /// a genuine cycle of glob imports (`a::*` -> `b::*` -> `a::*`) apart
+    /// from an import that is merely waiting its turn in the fixed-point
+    /// loop below. Only the most recently blocked glob is tracked per
+    /// module, which is enough to catch the common case of a ring where
+    /// each module has a single `use other::*`.
+    ///
+    /// This is keyed per-module rather than per-glob-directive, so a
+    /// module with more than one `pub use ...::*` only remembers the
+    /// latest one that got stuck; a cycle that runs through an earlier,
+    /// already-overwritten glob in such a module can be missed. That's
+    /// not a soundness issue — `find_glob_import_cycle` returning `None`
+    /// just falls back to the ordinary "unresolved import" diagnostic
+    /// instead of the more precise cycle error — but it does mean this
+    /// map can't be relied on to catch every cycle. Keying on the import
+    /// directive instead of the module would close the gap, if it turns
+    /// out to matter in practice.
+    glob_blocked_on: HashMap<DefId, (DefId, Rc<Module>, Span)>,
 }
 
 impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
@@ -377,7 +424,8 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
     fn get_binding(&mut self,
                    import_resolution: &ImportResolution,
                    namespace: Namespace,
-                   source: Name)
+                   source: Name,
+                   use_span: Span)
                    -> ResolveResult<(Rc<Module>, NsDef)> {
         // Import resolutions must be declared with "pub"
         // in order to be exported.
@@ -385,9 +433,23 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
             return Failed(None);
         }
 
+        if let Some(ref ambiguous) = import_resolution.ambiguous {
+            if let Some(ref target) = import_resolution.target {
+                span_err!(self.resolver.session,
+                          use_span,
+                          E0258,
+                          "`{}` is ambiguous",
+                          source);
+                self.resolver.session.span_note(target.span,
+                                                "could refer to the name imported here");
+                self.resolver.session.span_note(ambiguous.span,
+                                                "could also refer to the name imported here");
+            }
+        }
+
         match import_resolution.target.clone() {
             None => Failed(None),
-            Some(Target { target_module, ns_def, shadowable: _ }) => {
+            Some(Target { target_module, ns_def, .. }) => {
                 debug!("(resolving single import) found import in ns {:?}", namespace);
                 let id = import_resolution.id;
                 // track used imports and extern crates as well
@@ -484,7 +546,8 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
 
         let result = self.resolve_name(module, name, ns, directive, pub_err);
         let result = result.or(|| {
-            self.resolve_in_imports(module, name, ns, origin_module, &mut used_reexport)
+            self.resolve_in_imports(module, name, ns, origin_module, directive.span,
+                                    &mut used_reexport)
         });
         if let Indeterminate = result { return (Indeterminate, used_reexport) }
 
@@ -517,7 +580,9 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                           module: &Module,
                           name: Name,
                           ns: Namespace,
-                          origin_module: &Module, used: &mut bool)
+                          origin_module: &Module,
+                          use_span: Span,
+                          used: &mut bool)
                           -> ResolveResult<(Rc<Module>, NsDef)> {
         // If there is an unresolved glob at this point in the
         // containing module, bail out. We don't know enough to be
@@ -537,7 +602,7 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
             // We can, therefore, just follow it.
             Some(import_resolution) if import_resolution.outstanding_references == 0 => {
                 *used = import_resolution.is_public;
-                self.get_binding(import_resolution, ns, name)
+                self.get_binding(import_resolution, ns, name, use_span)
             },
 
             // If module is the same as the original module whose import we are resolving and
@@ -573,16 +638,22 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                 debug!("(resolving single import) found {:?} target: {:?}",
                        ns_name,
                        ns_def.def());
-                self.check_for_conflicting_import(&import_resolution,
-                                                  directive.span,
-                                                  target, ns);
 
                 self.check_that_import_is_importable(ns_def, directive.span, target);
 
-                let target = Target::new(target_module.clone(),
-                                         ns_def.clone(),
-                                         directive.shadowable);
-                import_resolution.target = Some(target);
+                let new_target = Target::new(target_module.clone(),
+                                             ns_def.clone(),
+                                             directive.shadowable,
+                                             false,
+                                             directive.span);
+                // An explicit import always wins over whatever was here
+                // before (in particular, a glob binding of the same name),
+                // so we don't need to gate on the result here the way the
+                // glob-merging call sites do.
+                self.check_for_conflicting_import(import_resolution,
+                                                  directive.span,
+                                                  target, ns, &new_target);
+                import_resolution.target = Some(new_target);
                 import_resolution.id = directive.id;
                 import_resolution.is_public = directive.is_public;
 
@@ -620,11 +691,7 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
             None => return,
         };
 
-        let priv_dep = if used_public {
-            lp
-        } else {
-            DependsOn(def.def_id())
-        };
+        let priv_dep = if used_public { lp } else { DependsOn(def.def_id()) };
 
         let mut def_map = self.resolver.def_map.borrow_mut();
         let mut resolution = def_map.entry(directive.id).or_insert_with(|| {
@@ -669,6 +736,29 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
         // (including globs).
         if (*target_module).pub_count.get() > 0 {
             debug!("(resolving glob import) target module has unresolved pub imports; bailing out");
+
+            if let (Some(module_id), Some(target_id)) = (module_.def_id(), target_module.def_id()) {
+                self.glob_blocked_on.insert(module_id,
+                                            (target_id, target_module.clone(), import_directive.span));
+
+                if let Some(cycle) = self.find_glob_import_cycle(module_id) {
+                    let mut parts = vec![format!("`{}::*`", module_to_string(module_))];
+                    parts.extend(cycle.iter().map(|m| format!("`{}::*`", module_to_string(&*m))));
+                    span_err!(self.resolver.session,
+                              import_directive.span,
+                              E0257,
+                              "glob import cycle: {}",
+                              parts.join(" -> "));
+
+                    // Break the cycle deterministically instead of looping
+                    // until the fixed-point loop in `resolve_imports` gives
+                    // up: treat the directive as resolved, contributing
+                    // nothing.
+                    self.glob_blocked_on.remove(&module_id);
+                    return Success(());
+                }
+            }
+
             return Indeterminate;
         }
 
@@ -697,14 +787,24 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                 if !target_import_resolution.is_public { continue }
 
                 if let Some(ref target) = target_import_resolution.target {
-                    self.check_for_conflicting_import(&dest_import_resolution,
-                                                      import_directive.span,
-                                                      name,
-                                                      ns);
-                    dest_import_resolution.target = Some(target.clone());
-                    dest_import_resolution.is_public = is_public;
+                    // This binding is reaching `module_` through our glob, so
+                    // it counts as glob-derived here even if it wasn't in
+                    // `target_module`.
+                    let new_target = Target::new(target.target_module.clone(),
+                                                 target.ns_def.clone(),
+                                                 target.shadowable,
+                                                 true,
+                                                 import_directive.span);
+                    if self.check_for_conflicting_import(dest_import_resolution,
+                                                         import_directive.span,
+                                                         name,
+                                                         ns,
+                                                         &new_target) {
+                        dest_import_resolution.target = Some(new_target);
+                        dest_import_resolution.is_public = is_public;
+                    }
                 }
-                
+
                 continue
             }
 
@@ -712,7 +812,13 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
             let mut new_import_resolution = ImportResolution::new(id, is_public);
             if !target_import_resolution.is_public { continue }
             new_import_resolution.target =
-                target_import_resolution.target.clone();
+                target_import_resolution.target.clone().map(|target| {
+                    Target::new(target.target_module,
+                               target.ns_def,
+                               target.shadowable,
+                               true,
+                               import_directive.span)
+                });
             import_resolutions.insert((name, ns), new_import_resolution);
         }
 
@@ -747,10 +853,42 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                                                       });
         }
 
+        // This directive isn't blocked on anything any more; drop any stale
+        // bookkeeping from an earlier `Indeterminate` result so it can't be
+        // mistaken for a still-live cycle edge later.
+        if let Some(module_id) = module_.def_id() {
+            self.glob_blocked_on.remove(&module_id);
+        }
+
         debug!("(resolving glob import) successfully resolved import");
         return Success(());
     }
 
+    /// Follows `glob_blocked_on` starting at `start`, looking for a path that
+    /// leads back to `start` itself. Returns the chain of target modules
+    /// (excluding `start`) if one is found.
+    fn find_glob_import_cycle(&self, start: DefId) -> Option<Vec<Rc<Module>>> {
+        let mut chain = Vec::new();
+        let mut current = start;
+        loop {
+            match self.glob_blocked_on.get(&current) {
+                Some(&(next, ref next_module, _)) => {
+                    chain.push(next_module.clone());
+                    if next == start {
+                        return Some(chain);
+                    }
+                    current = next;
+                }
+                None => return None,
+            }
+            if chain.len() > self.glob_blocked_on.len() {
+                // Safety valve: the bookkeeping above should never produce a
+                // path longer than the number of tracked edges.
+                return None;
+            }
+        }
+    }
+
     fn merge_import_resolution(&mut self,
                                module_: &Module,
                                containing_module: Rc<Module>,
@@ -780,6 +918,11 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                 ValueNS => "value",
             };
             debug!("(resolving glob import) ... for {} target", namespace_name);
+            let new_target = Target::new(containing_module.clone(),
+                                         ns_def.clone(),
+                                         import_directive.shadowable,
+                                         true,
+                                         import_directive.span);
             if dest_import_resolution.shadowable() == Shadowable::Never {
                 let msg = format!("a {} named `{}` has already been imported in this \
                                    module",
@@ -790,11 +933,12 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                          E0251,
                          "{}",
                         msg);
-           } else {
-                let target = Target::new(containing_module.clone(),
-                                         ns_def.clone(),
-                                         import_directive.shadowable);
-                dest_import_resolution.target = Some(target);
+           } else if self.check_for_conflicting_import(dest_import_resolution,
+                                                       import_directive.span,
+                                                       name,
+                                                       namespace,
+                                                       &new_target) {
+                dest_import_resolution.target = Some(new_target);
                 dest_import_resolution.id = id;
                 dest_import_resolution.is_public = is_public;
             }
@@ -806,18 +950,55 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                                                            (name, namespace));
     }
 
-    /// Checks that imported names and items don't have the same name.
+    /// Checks whether `new_target` may be written into `import_resolution`,
+    /// reports a hard conflict where two non-glob bindings collide, and
+    /// records an ambiguity if two globs disagree. Returns `true` if the
+    /// caller should go ahead and store `new_target` as the resolution's
+    /// target; after a hard conflict it still returns `true`.
+    ///
+    /// The shadowing rules, in order:
+    /// (a) an explicit import or local item always shadows a glob binding,
+    ///     silently, in either direction;
+    /// (b) two globs bringing in the same name are only a problem if they
+    ///     disagree on what the name refers to, and even then nothing is
+    ///     raised here: the disagreement is recorded in `ambiguous` and
+    ///     reported by `get_binding`;
+    /// (c) two non-glob bindings are a hard conflict (E0252), unless the
+    ///     existing one is `Shadowable::Always`.
     fn check_for_conflicting_import(&mut self,
-                                    import_resolution: &ImportResolution,
+                                    import_resolution: &mut ImportResolution,
                                     import_span: Span,
                                     name: Name,
-                                    namespace: Namespace) {
-        let target = import_resolution.target.clone();
+                                    namespace: Namespace,
+                                    new_target: &Target) -> bool {
+        let existing = import_resolution.target.clone();
         debug!("check_for_conflicting_import: {}; target exists: {}",
                name,
-               target.is_some());
-
-        match target {
+               existing.is_some());
+
+        match existing {
+            Some(ref existing) if existing.from_glob && !new_target.from_glob => {
+                // (a) the new, more specific binding wins over the old glob.
+                // It also settles any earlier glob-vs-glob disagreement:
+                // the name unambiguously means `new_target` now, so don't
+                // leave a stale ambiguity behind for `get_binding` to trip
+                // over at a later use site.
+                import_resolution.ambiguous = None;
+                true
+            }
+            Some(ref existing) if !existing.from_glob && new_target.from_glob => {
+                // (a) the existing, more specific binding wins; drop the glob.
+                false
+            }
+            Some(ref existing) if existing.from_glob && new_target.from_glob => {
+                // (b) two globs: only ambiguous if they actually disagree.
+                let existing_def = existing.ns_def.def().map(|d| d.def_id());
+                let new_def = new_target.ns_def.def().map(|d| d.def_id());
+                if existing_def != new_def {
+                    import_resolution.ambiguous = Some(new_target.clone());
+                }
+                false
+            }
             Some(ref target) if target.shadowable != Shadowable::Always => {
                 let ns_word = match namespace {
                     TypeNS => {
@@ -842,8 +1023,9 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
                            item.span,
                            "previous import of `{}` here",
                            name);
+                true
             }
-            Some(_) | None => {}
+            Some(_) | None => true,
         }
     }
 
@@ -890,7 +1072,11 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
 
         if let ValueNS = ns {
             match import_resolution.target {
-                Some(ref target) if target.shadowable != Shadowable::Always => {
+                // (a) A glob-derived binding just loses to the local item;
+                // that's not a conflict worth reporting. Only an explicit
+                // import colliding with a local item is a hard error.
+                Some(ref target) if target.shadowable != Shadowable::Always &&
+                                    !target.from_glob => {
                     span_err!(self.resolver.session,
                               import_span,
                               E0255,
@@ -904,7 +1090,9 @@ impl<'a, 'b:'a, 'tcx:'b> ImportResolver<'a, 'b, 'tcx> {
             }
         } else {
             match import_resolution.target {
-                Some(ref target) if target.shadowable != Shadowable::Always => {
+                // (a) same exemption as above, for types/modules/traits.
+                Some(ref target) if target.shadowable != Shadowable::Always &&
+                                    !target.from_glob => {
                     let (what, note) = match ns_def.module() {
                         Some(ref module) if module.is_normal() =>
                             ("existing submodule", "note conflicting module here"),
@@ -947,6 +1135,9 @@ fn import_directive_subclass_to_string(subclass: ImportDirectiveSubclass) -> Str
 }
 
 pub fn resolve_imports(resolver: &mut Resolver) {
-    let mut import_resolver = ImportResolver { resolver: resolver };
+    let mut import_resolver = ImportResolver {
+        resolver: resolver,
+        glob_blocked_on: HashMap::new(),
+    };
     import_resolver.resolve_imports();
 }
@@ -0,0 +1,22 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A ring of modules that each glob-import the next should be reported as a
+// glob import cycle rather than hanging the fixed-point resolver.
+
+mod a {
+    pub use b::*; //~ ERROR glob import cycle
+}
+
+mod b {
+    pub use a::*;
+}
+
+fn main() {}
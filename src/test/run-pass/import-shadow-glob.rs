@@ -0,0 +1,32 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// An explicit import of a name also brought in by a glob should silently
+// shadow the glob binding rather than triggering a conflict error, and
+// likewise for a local item of the same name.
+
+mod a {
+    pub fn f() -> isize { 1 }
+    pub fn g() -> isize { 1 }
+}
+
+mod b {
+    pub fn f() -> isize { 2 }
+}
+
+use a::*;
+use b::f; // explicit import shadows `a::f` brought in by the glob
+
+fn g() -> isize { 2 } // local item shadows `a::g` brought in by the glob
+
+fn main() {
+    assert_eq!(f(), 2);
+    assert_eq!(g(), 2);
+}
@@ -0,0 +1,39 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Regression test for `CfgSimplifier::simplify_known_branch` folding a
+// `SwitchInt`/`If` terminator whose discriminant turns out to be a
+// compile-time constant (here, after inlining `three()`) into a `Goto`.
+// The folded jump must still land on the same arm the original branch
+// would have taken. Note that this pass intentionally does *not* fold
+// the enum-discriminant `TerminatorKind::Switch` (see the comment next
+// to its omission in simplify_cfg.rs), so there's no equivalent case
+// for it here.
+
+fn three() -> i32 { 3 }
+
+fn describe(x: i32) -> &'static str {
+    match x {
+        1 => "one",
+        2 => "two",
+        3 => "three",
+        _ => "other",
+    }
+}
+
+fn main() {
+    assert_eq!(describe(three()), "three");
+    assert_eq!(describe(1), "one");
+    assert_eq!(describe(99), "other");
+
+    let cond = 1 == 1;
+    let label = if cond { "yes" } else { "no" };
+    assert_eq!(label, "yes");
+}
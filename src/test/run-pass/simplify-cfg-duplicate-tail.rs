@@ -0,0 +1,45 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Regression test for `CfgSimplifier::duplicate_tail`. A 4-arm `match`
+// lowers to a `SwitchInt` whose four arm blocks each set `tag` and then
+// jump into one shared tail block. Each arm block is a direct `SwitchInt`
+// successor with a single predecessor, so `duplicate_tail` may relocate
+// it; the shared tail is reached through `Goto`s rather than through the
+// branch's arms, so `duplicate_tail` leaves it shared. Each arm must
+// still return its own value of `tag`.
+
+fn classify(x: i32) -> &'static str {
+    match x {
+        0 => {
+            let tag = "zero";
+            tag
+        }
+        1 => {
+            let tag = "one";
+            tag
+        }
+        2 => {
+            let tag = "two";
+            tag
+        }
+        _ => {
+            let tag = "other";
+            tag
+        }
+    }
+}
+
+fn main() {
+    assert_eq!(classify(0), "zero");
+    assert_eq!(classify(1), "one");
+    assert_eq!(classify(2), "two");
+    assert_eq!(classify(5), "other");
+}
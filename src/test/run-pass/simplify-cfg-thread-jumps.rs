@@ -0,0 +1,24 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Regression test for `CfgSimplifier::thread_jumps`: `flag` is set from
+// an `if` in one block, then an unrelated block branches on `flag`
+// itself. Threading the jump from the assignment straight to the arm the
+// later branch would take must preserve which string `pick` returns.
+
+fn pick(which: bool) -> &'static str {
+    let flag = if which { true } else { false };
+    if flag { "first" } else { "second" }
+}
+
+fn main() {
+    assert_eq!(pick(true), "first");
+    assert_eq!(pick(false), "second");
+}